@@ -1,5 +1,6 @@
+use std::io;
 use std::iter;
-use std::io::{self, Write};
+use std::iter::FromIterator;
 use std::string::FromUtf8Error;
 
 const DEFAULT_CAPACITY: usize = 1024;
@@ -7,20 +8,44 @@ const MAX_UNICODE_WIDTH: usize = 4;
 
 /// This is a growable string builder.
 #[derive(Debug)]
-pub struct Builder(Vec<u8>);
+pub struct Builder {
+    buf: Vec<u8>,
+    // Bytes left over from a previous `append_checked` call that form the start of a multi-byte
+    // codepoint but weren't a complete codepoint on their own.
+    pending: [u8; 3],
+    pending_len: usize,
+}
 
 impl Default for Builder {
     fn default() -> Builder {
-        let inner = Vec::with_capacity(DEFAULT_CAPACITY);
-        Builder(inner)
+        Builder::new(DEFAULT_CAPACITY)
     }
 }
 
 impl Builder {
     /// Return a new `Builder` with an initial capacity.
     pub fn new(size: usize) -> Builder {
-        let inner = Vec::with_capacity(size);
-        Builder(inner)
+        Builder {
+            buf: Vec::with_capacity(size),
+            pending: [0; 3],
+            pending_len: 0,
+        }
+    }
+
+    /// Return a new `Builder` intended to be used with `append_checked` for incremental UTF-8
+    /// validation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::new_validated();
+    /// builder.append_checked("hello").unwrap();
+    /// assert_eq!(builder.finish().unwrap(), "hello");
+    /// ```
+    pub fn new_validated() -> Builder {
+        Builder::new(DEFAULT_CAPACITY)
     }
 
     /// Add a type that can be viewed as a slice of bytes.
@@ -34,7 +59,60 @@ impl Builder {
     /// builder.append("some string").unwrap();
     /// ```
     pub fn append<T: ToBytes>(&mut self, buf: T) -> io::Result<()> {
-        self.0.write_all(buf.to_bytes().as_slice())
+        buf.append_to(&mut self.buf);
+        Ok(())
+    }
+
+    /// Add a type that can be viewed as a slice of bytes, validating incrementally that the
+    /// buffer remains valid UTF-8.
+    ///
+    /// Unlike `append`, this catches invalid UTF-8 as soon as it is written rather than when
+    /// `string()` is finally called, and correctly handles multi-byte codepoints that are split
+    /// across two separate appends.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::new_validated();
+    /// builder.append_checked(&[0xe2, 0x82][..]).unwrap();
+    /// builder.append_checked(&[0xac][..]).unwrap();
+    /// assert_eq!(builder.finish().unwrap(), "\u{20ac}");
+    /// ```
+    pub fn append_checked<T: ToBytes>(&mut self, buf: T) -> Result<(), Utf8BuildError> {
+        let mut combined = Vec::with_capacity(self.pending_len + MAX_UNICODE_WIDTH);
+        combined.extend_from_slice(&self.pending[..self.pending_len]);
+        buf.append_to(&mut combined);
+
+        match std::str::from_utf8(&combined) {
+            Ok(_) => {
+                self.buf.extend_from_slice(&combined);
+                self.pending_len = 0;
+                Ok(())
+            }
+            Err(e) => {
+                let valid_up_to = e.valid_up_to();
+                let offset = self.buf.len() + valid_up_to;
+                self.buf.extend_from_slice(&combined[..valid_up_to]);
+
+                match e.error_len() {
+                    // The trailing bytes are a valid but incomplete codepoint prefix; stash them
+                    // for the next call.
+                    None => {
+                        let tail = &combined[valid_up_to..];
+                        self.pending[..tail.len()].copy_from_slice(tail);
+                        self.pending_len = tail.len();
+                        Ok(())
+                    }
+                    // The trailing bytes are a genuinely invalid sequence.
+                    Some(_) => {
+                        self.pending_len = 0;
+                        Err(Utf8BuildError::InvalidSequence { offset })
+                    }
+                }
+            }
+        }
     }
 
     /// Return the current length in bytes of the underlying buffer.
@@ -49,7 +127,7 @@ impl Builder {
     /// assert_eq!(builder.len(), 4);
     /// ```
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.buf.len()
     }
 
     /// Return a `String` of our buffer once we are done appending to it. This method consumes
@@ -67,13 +145,257 @@ impl Builder {
     /// assert_eq!(builder.string().unwrap(), "i am building a string");
     /// ```
     pub fn string(self) -> Result<String, FromUtf8Error> {
-        String::from_utf8(self.0)
+        String::from_utf8(self.buf)
+    }
+
+    /// Like `string()`, but for builders built up with `append_checked`. Returns an error if the
+    /// buffer ends with an incomplete multi-byte codepoint.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::new_validated();
+    /// builder.append_checked("hello").unwrap();
+    /// assert_eq!(builder.finish().unwrap(), "hello");
+    /// ```
+    pub fn finish(self) -> Result<String, Utf8BuildError> {
+        if self.pending_len != 0 {
+            return Err(Utf8BuildError::Incomplete);
+        }
+
+        String::from_utf8(self.buf).map_err(|e| match e.utf8_error().error_len() {
+            Some(_) => Utf8BuildError::InvalidSequence {
+                offset: e.utf8_error().valid_up_to(),
+            },
+            None => Utf8BuildError::Incomplete,
+        })
+    }
+
+    /// Return the number of bytes the underlying buffer can hold without reallocating.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let builder = Builder::new(16);
+    /// assert_eq!(builder.capacity(), 16);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Reserve capacity for at least `additional` more bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::new(0);
+    /// builder.reserve(16);
+    /// assert!(builder.capacity() >= 16);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(additional);
+    }
+
+    /// Clear the buffer, removing everything that has been appended so far so the `Builder` can
+    /// be reused.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::default();
+    /// builder.append("hello").unwrap();
+    /// builder.clear();
+    /// assert!(builder.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.pending_len = 0;
+    }
+
+    /// Shorten the buffer to `new_len` bytes, dropping anything appended after that point. Does
+    /// nothing if `new_len` is greater than the current length.
+    ///
+    /// This only works on byte boundaries: truncating in the middle of a multi-byte codepoint
+    /// leaves the buffer with invalid UTF-8, which `string()`/`finish()` will then report.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::default();
+    /// builder.append("hello world").unwrap();
+    /// builder.truncate(5);
+    /// assert_eq!(builder.string().unwrap(), "hello");
+    /// ```
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len <= self.buf.len() {
+            self.pending_len = 0;
+        }
+        self.buf.truncate(new_len);
+    }
+
+    /// Return `true` if the underlying buffer holds no bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let builder = Builder::default();
+    /// assert!(builder.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Borrow the underlying buffer as a byte slice without consuming the `Builder`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::default();
+    /// builder.append("hi").unwrap();
+    /// assert_eq!(builder.as_bytes(), b"hi");
+    /// ```
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Borrow the underlying buffer as a `&str` without consuming the `Builder`, so intermediate
+    /// results can be checked before giving up ownership via `string()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use string_builder::Builder;
+    ///
+    /// let mut builder = Builder::default();
+    /// builder.append("hi").unwrap();
+    /// assert_eq!(builder.as_str().unwrap(), "hi");
+    /// ```
+    pub fn as_str(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.buf)
+    }
+}
+
+/// This lets a `Builder` be used as the destination of the `write!`/`writeln!` macros, the same
+/// way `std::string::String` can be.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use string_builder::Builder;
+///
+/// let mut builder = Builder::default();
+/// write!(builder, "{}: {}", "key", 42).unwrap();
+/// assert_eq!(builder.string().unwrap(), "key: 42");
+/// ```
+impl std::fmt::Write for Builder {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.buf.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> std::fmt::Result {
+        let mut tmp = [0; MAX_UNICODE_WIDTH];
+        self.buf
+            .extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
+        Ok(())
+    }
+}
+
+/// Extend a `Builder` with an iterator of any type that already implements `ToBytes`, the same
+/// way `std::string::String` can be extended from an iterator of `char` or `&str`.
+///
+/// # Example
+///
+/// ```rust
+/// use string_builder::Builder;
+///
+/// let mut builder = Builder::default();
+/// builder.extend(vec!["foo", " ", "bar"]);
+/// assert_eq!(builder.string().unwrap(), "foo bar");
+/// ```
+impl<A: ToBytes> Extend<A> for Builder {
+    fn extend<T: IntoIterator<Item = A>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        self.buf.reserve(lower);
+
+        for item in iter {
+            item.append_to(&mut self.buf);
+        }
+    }
+}
+
+/// Build a `Builder` directly from an iterator of any type that already implements `ToBytes`.
+///
+/// # Example
+///
+/// ```rust
+/// use string_builder::Builder;
+///
+/// let builder: Builder = vec!['a', 'b', 'c'].into_iter().collect();
+/// assert_eq!(builder.string().unwrap(), "abc");
+/// ```
+impl<A: ToBytes> FromIterator<A> for Builder {
+    fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Builder {
+        let mut builder = Builder::default();
+        builder.extend(iter);
+        builder
     }
 }
 
+/// An error returned by `append_checked` and `finish` when incremental UTF-8 validation fails.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Utf8BuildError {
+    /// An invalid UTF-8 byte sequence was found, starting at this byte offset in the builder.
+    InvalidSequence { offset: usize },
+    /// The builder was finished while the buffer ended with an incomplete multi-byte codepoint.
+    Incomplete,
+}
+
+impl std::fmt::Display for Utf8BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Utf8BuildError::InvalidSequence { offset } => {
+                write!(
+                    f,
+                    "invalid UTF-8 sequence starting at byte offset {}",
+                    offset
+                )
+            }
+            Utf8BuildError::Incomplete => {
+                write!(f, "buffer ended with an incomplete UTF-8 codepoint")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Utf8BuildError {}
+
 /// A trait to convert a value into a byte slice that can be appended to a `Builder`.
 pub trait ToBytes {
     fn to_bytes(&self) -> Vec<u8>;
+
+    /// Write the bytes for this value directly into `buf` instead of allocating an intermediate
+    /// `Vec`. Implementors should override this; the default falls back to `to_bytes` so that
+    /// existing external impls keep compiling unchanged.
+    fn append_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_bytes());
+    }
 }
 
 // Generate a buffer with the same length as the given argument in order to use `copy_from_slice`.
@@ -92,18 +414,30 @@ impl ToBytes for String {
     fn to_bytes(&self) -> Vec<u8> {
         slice_to_vec(self.as_bytes())
     }
+
+    fn append_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
 }
 
 impl<'a> ToBytes for &'a str {
     fn to_bytes(&self) -> Vec<u8> {
         slice_to_vec(self.as_bytes())
     }
+
+    fn append_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
 }
 
 impl ToBytes for u8 {
     fn to_bytes(&self) -> Vec<u8> {
         vec![*self]
     }
+
+    fn append_to(&self, buf: &mut Vec<u8>) {
+        buf.push(*self);
+    }
 }
 
 impl ToBytes for char {
@@ -112,17 +446,27 @@ impl ToBytes for char {
         let mut buf = [0; MAX_UNICODE_WIDTH];
         slice_to_vec(self.encode_utf8(&mut buf).as_bytes())
     }
+
+    fn append_to(&self, buf: &mut Vec<u8>) {
+        // The maximum length of a unicode character is 4 bytes.
+        let mut tmp = [0; MAX_UNICODE_WIDTH];
+        buf.extend_from_slice(self.encode_utf8(&mut tmp).as_bytes());
+    }
 }
 
 impl<'a> ToBytes for &'a [u8] {
     fn to_bytes(&self) -> Vec<u8> {
         slice_to_vec(self)
     }
+
+    fn append_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Builder;
+    use super::{Builder, Utf8BuildError};
 
     #[test]
     fn test_all_supported_types() {
@@ -154,4 +498,100 @@ mod tests {
 
         assert_eq!(b.string().unwrap(), "\u{00C6}nima");
     }
+
+    #[test]
+    fn test_append_checked_split_codepoint() {
+        let mut b = Builder::new_validated();
+        // The euro sign, split across two appends right in the middle of its 3-byte encoding.
+        b.append_checked(&[0xe2, 0x82][..]).unwrap();
+        b.append_checked(&[0xac][..]).unwrap();
+
+        assert_eq!(b.finish().unwrap(), "\u{20ac}");
+    }
+
+    #[test]
+    fn test_append_checked_invalid_sequence() {
+        let mut b = Builder::new_validated();
+        b.append_checked("good").unwrap();
+        let err = b.append_checked(&[0xff][..]).unwrap_err();
+
+        assert_eq!(err, Utf8BuildError::InvalidSequence { offset: 4 });
+    }
+
+    #[test]
+    fn test_finish_rejects_incomplete_trailing_codepoint() {
+        let mut b = Builder::new_validated();
+        b.append_checked(&[0xe2, 0x82][..]).unwrap();
+
+        assert_eq!(b.finish().unwrap_err(), Utf8BuildError::Incomplete);
+    }
+
+    #[test]
+    fn test_fmt_write() {
+        use std::fmt::Write;
+
+        let mut b = Builder::default();
+        let key = "key";
+        write!(b, "{}: {}", key, 42).unwrap();
+        b.write_char('!').unwrap();
+
+        assert_eq!(b.string().unwrap(), "key: 42!");
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut b = Builder::default();
+        b.extend(vec!["foo", " ", "bar"]);
+        b.extend(vec!['!', '?']);
+
+        assert_eq!(b.string().unwrap(), "foo bar!?");
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let b: Builder = vec!['a', 'b', 'c'].into_iter().collect();
+
+        assert_eq!(b.string().unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_capacity_and_reserve() {
+        let mut b = Builder::new(0);
+        assert_eq!(b.capacity(), 0);
+
+        b.reserve(32);
+        assert!(b.capacity() >= 32);
+    }
+
+    #[test]
+    fn test_clear_and_is_empty() {
+        let mut b = Builder::default();
+        assert!(b.is_empty());
+
+        b.append("hello").unwrap();
+        assert!(!b.is_empty());
+
+        b.clear();
+        assert!(b.is_empty());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut b = Builder::default();
+        b.append("hello world").unwrap();
+        b.truncate(5);
+
+        assert_eq!(b.string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_as_bytes_and_as_str() {
+        let mut b = Builder::default();
+        b.append("hi").unwrap();
+
+        assert_eq!(b.as_bytes(), b"hi");
+        assert_eq!(b.as_str().unwrap(), "hi");
+        // The `Builder` is still usable after borrowing it.
+        assert_eq!(b.string().unwrap(), "hi");
+    }
 }